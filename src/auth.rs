@@ -0,0 +1,118 @@
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+/// Guards the write routes behind an `Authorization: Bearer <token>` header
+/// checked against the configured API key. When no key is configured the
+/// guard is a no-op, so the server stays usable without auth by default.
+pub async fn require_api_key(
+    State(api_key): State<Option<String>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(expected) = api_key else {
+        return next.run(request).await;
+    };
+
+    let provided = request
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    if !matches!(provided, Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes())) {
+        return (StatusCode::UNAUTHORIZED, "invalid or missing API key").into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Compares two byte strings in time independent of where they first differ,
+/// so a timing side-channel can't be used to guess the API key one byte at a
+/// time. A length mismatch is still observable, but lengths aren't secret.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+        == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{header, Request};
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    fn app(api_key: Option<String>) -> Router {
+        Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(
+                api_key,
+                require_api_key,
+            ))
+    }
+
+    async fn status(app: Router, auth_header: Option<&str>) -> StatusCode {
+        let mut request = Request::builder().uri("/");
+        if let Some(value) = auth_header {
+            request = request.header(header::AUTHORIZATION, value);
+        }
+        let response = app
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        response.status()
+    }
+
+    #[tokio::test]
+    async fn passes_through_when_no_key_is_configured() {
+        assert_eq!(status(app(None), None).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_authorization_header() {
+        assert_eq!(
+            status(app(Some("secret".to_string())), None).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_token() {
+        assert_eq!(
+            status(app(Some("secret".to_string())), Some("Bearer wrong")).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[tokio::test]
+    async fn accepts_correct_token() {
+        assert_eq!(
+            status(app(Some("secret".to_string())), Some("Bearer secret")).await,
+            StatusCode::OK
+        );
+    }
+
+    #[test]
+    fn constant_time_eq_matches_equal_bytes() {
+        assert!(constant_time_eq(b"matching-token", b"matching-token"));
+    }
+
+    #[test]
+    fn constant_time_eq_rejects_different_length() {
+        assert!(!constant_time_eq(b"short", b"much longer value"));
+    }
+
+    #[test]
+    fn constant_time_eq_rejects_same_length_mismatch() {
+        assert!(!constant_time_eq(b"abcdef", b"abcxef"));
+    }
+}
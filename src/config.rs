@@ -0,0 +1,20 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// Command-line (and environment-variable) configuration for the file server.
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+pub struct Config {
+    /// Address to bind the server to
+    #[arg(long, env = "HOST", default_value = "0.0.0.0")]
+    pub host: String,
+
+    /// Port to listen on
+    #[arg(long, env = "PORT", default_value_t = 3000)]
+    pub port: u16,
+
+    /// Directory served as the content root
+    #[arg(long, env = "ROOT", default_value = ".")]
+    pub root: PathBuf,
+}
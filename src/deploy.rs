@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::extract::State;
+use axum::response::{IntoResponse, Response};
+use futures_util::TryStreamExt;
+use tokio_util::io::StreamReader;
+
+use crate::error::AppError;
+use crate::AppState;
+
+/// Accepts a streamed gzipped tarball and swaps its contents in as the
+/// server's content root.
+///
+/// The incoming body is written to a temp file first (named with a UUID so
+/// concurrent deploys don't collide), then unpacked inside `spawn_blocking`
+/// since the `tar` crate is synchronous. The archive is extracted into a
+/// staging directory next to `root` — same filesystem, so the swap below is
+/// a pair of cheap directory renames rather than a copy — and then swapped
+/// into place: the live root is renamed aside and the staging directory is
+/// renamed into its place. Anything the tarball doesn't include is dropped
+/// along with the old root, so this also prunes stale files for free.
+///
+/// Readers never see a half-extracted tree, but the swap itself is not
+/// atomic: between the two renames the root path does not exist, and reads
+/// that land in that window get a `404`.
+pub async fn handle_deploy(
+    State(state): State<Arc<AppState>>,
+    body: Body,
+) -> Result<Response, AppError> {
+    let deploy_id = uuid::Uuid::new_v4();
+    let archive_path = state.root_parent.join(format!(".deploy-{deploy_id}.tar.gz"));
+    let staging_dir = state.root_parent.join(format!(".deploy-{deploy_id}"));
+    let previous_dir = state
+        .root_parent
+        .join(format!(".deploy-{deploy_id}-previous"));
+
+    let stream = body
+        .into_data_stream()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e));
+    let mut reader = StreamReader::new(stream);
+    let mut archive_file = tokio::fs::File::create(&archive_path).await?;
+    tokio::io::copy(&mut reader, &mut archive_file).await?;
+    drop(archive_file);
+
+    let staging_dir_for_blocking = staging_dir.clone();
+    let archive_path_for_blocking = archive_path.clone();
+    let extracted = tokio::task::spawn_blocking(move || {
+        extract_tarball(&archive_path_for_blocking, &staging_dir_for_blocking)
+    })
+    .await
+    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))??;
+
+    tokio::fs::remove_file(&archive_path).await.ok();
+
+    // Block any in-flight upload's containment check from straddling the
+    // swap below, which would otherwise let it resolve against a root that
+    // changed underneath it mid-request.
+    let _deploy_guard = state.deploy_lock.write().await;
+    tokio::fs::rename(&state.root, &previous_dir).await?;
+    if let Err(e) = tokio::fs::rename(&staging_dir, &state.root).await {
+        // Best-effort rollback so a failed swap doesn't leave the root missing.
+        tokio::fs::rename(&previous_dir, &state.root).await.ok();
+        return Err(e.into());
+    }
+    tokio::fs::remove_dir_all(&previous_dir).await.ok();
+
+    Ok(format!("deployed {} files\n", extracted.len()).into_response())
+}
+
+/// Unpacks `archive_path` (a gzipped tarball) into `dest_dir`, returning the
+/// set of paths (relative to `dest_dir`) it wrote.
+fn extract_tarball(archive_path: &Path, dest_dir: &Path) -> std::io::Result<HashSet<PathBuf>> {
+    std::fs::create_dir_all(dest_dir)?;
+
+    let file = std::fs::File::open(archive_path)?;
+    let decoder = flate2::read::GzDecoder::new(file);
+    let mut archive = tar::Archive::new(decoder);
+
+    let mut extracted = HashSet::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let relative_path = entry.path()?.into_owned();
+        // `unpack_in` returns false for entries it skips (e.g. ones with `..`).
+        if entry.unpack_in(dest_dir)? {
+            extracted.insert(relative_path);
+        }
+    }
+
+    Ok(extracted)
+}
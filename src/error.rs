@@ -0,0 +1,47 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+/// Unified error type for the handlers, so filesystem failures turn into an
+/// HTTP response instead of panicking the worker.
+#[derive(Debug)]
+pub enum AppError {
+    NotFound,
+    Forbidden,
+    BadRequest(String),
+    Io(std::io::Error),
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::NotFound => AppError::NotFound,
+            std::io::ErrorKind::PermissionDenied => AppError::Forbidden,
+            _ => AppError::Io(err),
+        }
+    }
+}
+
+impl From<axum::extract::multipart::MultipartError> for AppError {
+    fn from(err: axum::extract::multipart::MultipartError) -> Self {
+        AppError::BadRequest(err.to_string())
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            AppError::NotFound => (StatusCode::NOT_FOUND, "not found".to_string()),
+            AppError::Forbidden => (StatusCode::FORBIDDEN, "forbidden".to_string()),
+            AppError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            AppError::Io(err) => {
+                tracing::error!("unhandled io error: {err}");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "internal server error".to_string(),
+                )
+            }
+        };
+
+        (status, message).into_response()
+    }
+}
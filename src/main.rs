@@ -1,10 +1,42 @@
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::Arc;
+
 use axum::body::Body;
-use axum::extract::Path;
+use axum::extract::{Multipart, Path, Query, State};
 use axum::http::StatusCode;
 use axum::response::{Html, IntoResponse, Response};
 use axum::Router;
 use axum::routing::get;
+use clap::Parser;
 use mime_guess::mime;
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+
+mod auth;
+mod config;
+mod deploy;
+mod error;
+mod metrics;
+use config::Config;
+use deploy::handle_deploy;
+use error::AppError;
+use metrics_exporter_prometheus::PrometheusHandle;
+
+/// Shared server state: the canonicalized directory every request is served
+/// from and confined to, its parent (where `/deploy` stages same-filesystem
+/// swaps), a lock serializing `/deploy` swaps against uploads, plus the
+/// Prometheus handle used to render metrics.
+pub struct AppState {
+    pub root: PathBuf,
+    pub root_parent: PathBuf,
+    /// `/deploy` holds this for writing while it swaps the root out from
+    /// under any path an upload might be resolving; uploads hold it for
+    /// reading only for the duration of `resolve_write_dir`, so a swap can't
+    /// land between its containment check and the directory creation it
+    /// guards.
+    pub deploy_lock: tokio::sync::RwLock<()>,
+    pub metrics_handle: PrometheusHandle,
+}
 
 /// 1. launch Axum server
 /// 1.1 add the dependency
@@ -18,8 +50,6 @@ use mime_guess::mime;
 ///
 /// TODO:
 /// 1. logging and tracing requests
-/// 2. command line argument, e.g. port
-/// 3. streaming file content
 ///
 /// To understand more about axum:
 /// 1. youtube: https://youtu.be/Wnb_n5YktO8?si=hjVeUfJizLvDnflM
@@ -29,73 +59,416 @@ async fn main() {
     // initialize tracing
     tracing_subscriber::fmt::init();
 
-    // build our application with a route
-    let app = Router::new()
+    let config = Config::parse();
+    let root = tokio::fs::canonicalize(&config.root)
+        .await
+        .unwrap_or_else(|e| panic!("failed to resolve root {:?}: {e}", config.root));
+    let root_parent = root
+        .parent()
+        .unwrap_or_else(|| panic!("root {root:?} has no parent directory to stage deploys alongside; pick a root other than a filesystem's top level"))
+        .to_path_buf();
+    let metrics_handle = metrics::install_recorder();
+    let state = Arc::new(AppState {
+        root,
+        root_parent,
+        deploy_lock: tokio::sync::RwLock::new(()),
+        metrics_handle,
+    });
+
+    // an API key configured via the `API_KEY` env var guards the write routes;
+    // leaving it unset keeps the server open, matching the previous behavior
+    let api_key = std::env::var("API_KEY").ok();
+
+    let write_routes = Router::new()
+        .route("/deploy", axum::routing::post(handle_deploy))
+        .route("/*path", axum::routing::post(handle_upload))
+        .layer(axum::middleware::from_fn_with_state(
+            api_key,
+            auth::require_api_key,
+        ));
+
+    let read_routes = Router::new()
         .route("/", get(list_wd))
+        .route("/metrics", get(metrics::metrics_handler))
         .route("/*path", get(handle_path));
 
-    // run our app with hyper, listening globally on port 3000
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
+    // build our application with a route
+    let app = read_routes
+        .merge(write_routes)
+        .route_layer(axum::middleware::from_fn(metrics::track_metrics))
+        .with_state(state);
+
+    // run our app with hyper, listening on the configured host and port
+    let addr = format!("{}:{}", config.host, config.port);
+    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+    tracing::info!("listening on {addr}");
     axum::serve(listener, app).await.unwrap();
 }
 
-async fn handle_path(Path(path): Path<String>) -> Response {
-    let fs_path_str = format!("./{}", path);
+/// Resolves `requested` against `root`, canonicalizing the result and
+/// rejecting anything that would escape `root` (e.g. via `..` components,
+/// symlinks, or a leading `/` that would otherwise make `PathBuf::join`
+/// discard `root` entirely).
+async fn resolve_path(root: &FsPath, requested: &str) -> Result<PathBuf, AppError> {
+    let joined = root.join(requested.trim_start_matches('/'));
+    let resolved = tokio::fs::canonicalize(&joined).await?;
+
+    if !resolved.starts_with(root) {
+        return Err(AppError::Forbidden);
+    }
+
+    Ok(resolved)
+}
+
+/// Resolves the directory a write request should land in, the same way
+/// `resolve_path` does for reads. Unlike `resolve_path`, the destination
+/// doesn't have to exist yet: this confirms containment against the closest
+/// existing ancestor *before* creating anything, then creates the directory
+/// and re-checks the final, fully resolved path.
+async fn resolve_write_dir(root: &FsPath, requested: &str) -> Result<PathBuf, AppError> {
+    if FsPath::new(requested)
+        .components()
+        .any(|c| c == std::path::Component::ParentDir)
+    {
+        return Err(AppError::BadRequest(
+            "path traversal is not allowed".to_string(),
+        ));
+    }
+
+    let joined = root.join(requested.trim_start_matches('/'));
+    ensure_ancestor_contained(root, &joined).await?;
+    tokio::fs::create_dir_all(&joined).await?;
+
+    let resolved = tokio::fs::canonicalize(&joined).await?;
+    if !resolved.starts_with(root) {
+        return Err(AppError::Forbidden);
+    }
+
+    Ok(resolved)
+}
+
+/// Walks up from `target` to the nearest ancestor that already exists and
+/// confirms it canonicalizes inside `root`. A symlink planted under `root`
+/// (e.g. via an extracted deploy tarball) can point anywhere, so this has to
+/// run *before* `create_dir_all` follows it and creates directories on the
+/// other side.
+async fn ensure_ancestor_contained(root: &FsPath, target: &FsPath) -> Result<(), AppError> {
+    let mut ancestor = target;
+    loop {
+        match tokio::fs::metadata(ancestor).await {
+            Ok(_) => {
+                let resolved = tokio::fs::canonicalize(ancestor).await?;
+                return if resolved.starts_with(root) {
+                    Ok(())
+                } else {
+                    Err(AppError::Forbidden)
+                };
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => match ancestor.parent() {
+                Some(parent) => ancestor = parent,
+                None => return Ok(()),
+            },
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
 
-    let path = std::path::Path::new(&fs_path_str);
+async fn handle_path(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Response, AppError> {
+    let path = resolve_path(&state.root, &path).await?;
+    let path = path.as_path();
 
     // Use Tokio to asynchronously retrieve metadata for the path
-    let metadata = tokio::fs::metadata(path).await.unwrap();
+    let metadata = tokio::fs::metadata(path).await?;
+
+    let wants_raw = params.get("raw").map(String::as_str) == Some("1");
+    let is_markdown = matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("md") | Some("markdown")
+    );
 
     if metadata.is_dir() {
-        return list_dir(path).await.into_response();
+        return list_dir(&state.root, path).await;
+    } else if is_markdown && !wants_raw {
+        let markdown = tokio::fs::read_to_string(path).await?;
+        return Ok(render_markdown(&markdown).into_response());
     } else if metadata.is_file() || metadata.is_symlink() {
         let guess = mime_guess::from_path(path).first();
         let mime_type = guess.unwrap_or(mime::APPLICATION_OCTET_STREAM);
-        let bytes = tokio::fs::read(path).await.unwrap();
-        return Response::builder()
+
+        // Stream the file chunk-by-chunk instead of buffering it whole into memory.
+        let file = tokio::fs::File::open(path).await?;
+        let content_length = file.metadata().await?.len();
+        let stream = ReaderStream::new(file);
+        let body = Body::from_stream(stream);
+
+        return Ok(Response::builder()
             .header(axum::http::header::CONTENT_TYPE, mime_type.to_string())
+            .header(axum::http::header::CONTENT_LENGTH, content_length)
             .status(StatusCode::OK)
-            .body(Body::from(bytes))
-            .unwrap();
+            .body(body)
+            .unwrap());
     } else {
-        return (
+        return Ok((
             StatusCode::INTERNAL_SERVER_ERROR,
             "unhandled type",
-        ).into_response();
+        ).into_response());
     }
 }
 
-/// path can be "." or "./foo/qoo"
-async fn list_dir(dir_path: &std::path::Path) -> Html<String> {
-    let url_dir_path = dir_path.strip_prefix(".").unwrap().as_os_str().to_str().unwrap();
-    let url_dir_path = format!("{}/", url_dir_path);
+/// Accepts a `multipart/form-data` upload and streams each field with a
+/// filename to disk under the target directory, so large uploads never
+/// buffer fully in memory.
+async fn handle_upload(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    mut multipart: Multipart,
+) -> Result<Response, AppError> {
+    // Scoped to resolve_write_dir alone so a concurrent `/deploy` swap can't
+    // land between its containment check and the create_dir_all it guards;
+    // released immediately after so a slow upload body can't stall deploys.
+    let target_dir = {
+        let _deploy_guard = state.deploy_lock.read().await;
+        resolve_write_dir(&state.root, &path).await?
+    };
+
+    let mut stored = Vec::new();
+
+    while let Some(mut field) = multipart.next_field().await? {
+        let Some(filename) = field.file_name().map(str::to_owned) else {
+            continue;
+        };
+
+        // An absolute filename would make `join` below discard `target_dir`.
+        if std::path::Path::new(&filename).components().any(|c| {
+            matches!(
+                c,
+                std::path::Component::ParentDir
+                    | std::path::Component::RootDir
+                    | std::path::Component::Prefix(_)
+            )
+        }) {
+            return Err(AppError::BadRequest(
+                "path traversal is not allowed".to_string(),
+            ));
+        }
+
+        let dest_path = target_dir.join(&filename);
+        let mut dest_file = tokio::fs::File::create(&dest_path).await?;
+
+        let mut bytes_written: u64 = 0;
+        while let Some(chunk) = field.chunk().await? {
+            dest_file.write_all(&chunk).await?;
+            bytes_written += chunk.len() as u64;
+        }
+
+        stored.push((filename, bytes_written));
+    }
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<title>Upload Result</title>\n</head>\n<body>\n");
+    html.push_str("<h1>Uploaded Files</h1>\n<ul>\n");
+    for (filename, bytes_written) in &stored {
+        html.push_str(&format!("<li>{} ({} bytes)</li>\n", filename, bytes_written));
+    }
+    html.push_str("</ul>\n</body>\n</html>");
+
+    Ok(Html(html).into_response())
+}
+
+/// Renders Markdown source to a minimal HTML document, so `.md`/`.markdown`
+/// files double as a lightweight documentation viewer. Pass `?raw=1` to get
+/// the original bytes instead.
+///
+/// The rendered body is sanitized with `ammonia` before being embedded, since
+/// `pulldown-cmark` passes inline/raw HTML through unescaped and this path is
+/// reachable from uploaded files.
+fn render_markdown(markdown: &str) -> Html<String> {
+    let parser = pulldown_cmark::Parser::new(markdown);
+    let mut unsafe_body = String::new();
+    pulldown_cmark::html::push_html(&mut unsafe_body, parser);
+    let body = ammonia::clean(&unsafe_body);
+
+    let html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<title>Markdown</title>\n</head>\n<body>\n{}\n</body>\n</html>",
+        body
+    );
+    Html(html)
+}
+
+/// `dir_path` can be `root` itself or any directory under it.
+async fn list_dir(root: &FsPath, dir_path: &FsPath) -> Result<Response, AppError> {
+    let relative = dir_path.strip_prefix(root).unwrap().to_string_lossy();
+    let url_dir_path = if relative.is_empty() {
+        "/".to_string()
+    } else {
+        format!("/{}/", relative)
+    };
 
     // Read the directory contents asynchronously
-    let mut entries = tokio::fs::read_dir(dir_path).await.unwrap();
+    let mut entries = tokio::fs::read_dir(dir_path).await?;
 
     // Create an HTML string
     let mut html = String::new();
     html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<title>Directory Listing</title>\n</head>\n<body>\n");
-    let header = format!("<h1>Directory Listing for {}</h1>\n<ul>\n", url_dir_path);
+    let header = format!(
+        "<h1>Directory Listing for {}</h1>\n<ul>\n",
+        escape_html(&url_dir_path)
+    );
     html.push_str(&header);
 
-    // Iterate over directory entries and add them to the HTML
-    while let Some(entry) = entries.next_entry().await.unwrap() {
+    // Iterate over directory entries and add them to the HTML. Entry names
+    // come straight from the filesystem and are attacker-controlled (e.g. an
+    // uploaded filename), so they're percent-encoded for the `href` and
+    // HTML-escaped for the displayed text rather than interpolated raw.
+    while let Some(entry) = entries.next_entry().await? {
         let entry_path = entry.path();
         let entry_name = entry_path.file_name().unwrap_or_default().to_string_lossy();
-        let meta = tokio::fs::metadata(&entry_path).await.unwrap();
-        let link = if meta.is_dir() { format!("{}/", entry_name) } else { entry_name.to_string() };
-        let link = format!("<a href={}>{}</a>", link, entry_name);
+        let meta = tokio::fs::metadata(&entry_path).await?;
+        let link_target = if meta.is_dir() {
+            format!("{}/", entry_name)
+        } else {
+            entry_name.to_string()
+        };
+        let link = format!(
+            "<a href=\"{}\">{}</a>",
+            percent_encode_path_segment(&link_target),
+            escape_html(&entry_name)
+        );
         html.push_str(&format!("<li>{}</li>\n", link));
     }
 
     html.push_str("</ul>\n</body>\n</html>");
-    return Html(html);
+    Ok(Html(html).into_response())
+}
+
+/// Escapes `&`, `<`, `>`, `"`, and `'` so untrusted strings (uploaded
+/// filenames, directory names) can be embedded in HTML text or a quoted
+/// attribute without breaking out of it.
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Percent-encodes everything but unreserved characters and `/`, so a path
+/// segment with spaces, `#`, `?`, or HTML-special characters still produces
+/// both a working and a safe-to-embed link.
+fn percent_encode_path_segment(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(*byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
 }
 
-/// List the content of working directory
+/// List the content of the served root directory
 /// Returns html document as a string
-async fn list_wd() -> Html<String> {
-    return list_dir(&std::path::Path::new(".")).await;
+async fn list_wd(State(state): State<Arc<AppState>>) -> Result<Response, AppError> {
+    list_dir(&state.root, &state.root).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A freshly created, canonicalized directory that's removed on drop,
+    /// mirroring the canonicalized `root` every `resolve_*` call receives in
+    /// production.
+    struct TempRoot {
+        path: PathBuf,
+    }
+
+    impl TempRoot {
+        async fn new() -> Self {
+            let raw =
+                std::env::temp_dir().join(format!("axum-tutorial-test-{}", uuid::Uuid::new_v4()));
+            tokio::fs::create_dir_all(&raw).await.unwrap();
+            let path = tokio::fs::canonicalize(&raw).await.unwrap();
+            Self { path }
+        }
+    }
+
+    impl Drop for TempRoot {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_path_normalizes_a_leading_slash() {
+        let root = TempRoot::new().await;
+        tokio::fs::write(root.path.join("file.txt"), b"hi")
+            .await
+            .unwrap();
+
+        let resolved = resolve_path(&root.path, "/file.txt").await.unwrap();
+
+        assert_eq!(resolved, root.path.join("file.txt"));
+    }
+
+    #[tokio::test]
+    async fn resolve_path_rejects_traversal_that_escapes_root() {
+        let root = TempRoot::new().await;
+        let outside = TempRoot::new().await;
+        tokio::fs::write(outside.path.join("secret.txt"), b"secret")
+            .await
+            .unwrap();
+
+        let traversal = format!(
+            "../{}/secret.txt",
+            outside.path.file_name().unwrap().to_string_lossy()
+        );
+        let result = resolve_path(&root.path, &traversal).await;
+
+        assert!(matches!(result, Err(AppError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn resolve_write_dir_rejects_parent_dir_components() {
+        let root = TempRoot::new().await;
+
+        let result = resolve_write_dir(&root.path, "../evil").await;
+
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn resolve_write_dir_creates_a_nested_leading_slash_path() {
+        let root = TempRoot::new().await;
+
+        let resolved = resolve_write_dir(&root.path, "/nested/dir").await.unwrap();
+
+        assert_eq!(resolved, root.path.join("nested/dir"));
+    }
+
+    #[tokio::test]
+    async fn resolve_write_dir_rejects_symlink_escape_without_creating_anything() {
+        let root = TempRoot::new().await;
+        let outside = TempRoot::new().await;
+
+        std::os::unix::fs::symlink(&outside.path, root.path.join("escape")).unwrap();
+
+        let result = resolve_write_dir(&root.path, "escape/new_dir").await;
+
+        assert!(matches!(result, Err(AppError::Forbidden)));
+        assert!(!outside.path.join("new_dir").exists());
+    }
 }
\ No newline at end of file
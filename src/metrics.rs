@@ -0,0 +1,63 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::header::CONTENT_LENGTH;
+use axum::middleware::Next;
+use axum::response::Response;
+use metrics::{counter, histogram};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+
+use crate::AppState;
+
+/// Installs the process-wide Prometheus recorder, returning a handle that
+/// can render the current metrics snapshot on demand.
+pub fn install_recorder() -> PrometheusHandle {
+    PrometheusBuilder::new()
+        .install_recorder()
+        .expect("failed to install Prometheus recorder")
+}
+
+/// `GET /metrics` — renders the current snapshot in Prometheus text format.
+pub async fn metrics_handler(State(state): State<Arc<AppState>>) -> String {
+    state.metrics_handle.render()
+}
+
+/// Records request counts, latency, and bytes served for every request that
+/// passes through the router, labelled by method, matched route, and status.
+pub async fn track_metrics(
+    matched_path: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().to_string();
+    let path = matched_path
+        .map(|matched_path| matched_path.as_str().to_owned())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let latency = start.elapsed().as_secs_f64();
+
+    let labels = [
+        ("method", method),
+        ("path", path),
+        ("status", response.status().as_u16().to_string()),
+    ];
+
+    counter!("http_requests_total", &labels).increment(1);
+    histogram!("http_request_duration_seconds", &labels).record(latency);
+
+    // Only the file-download branch of `handle_path` sets `Content-Length` up
+    // front, so this counter tracks bytes served for downloads specifically.
+    if let Some(bytes_served) = response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+    {
+        counter!("http_download_bytes_total", &labels).increment(bytes_served);
+    }
+
+    response
+}